@@ -0,0 +1,152 @@
+// How many recent ping/pong samples to keep around when picking the
+// lowest-jitter offset estimate.
+const WINDOW_SIZE: usize = 8;
+
+// How much of the gap between our current offset estimate and the window's
+// lowest-RTT sample to close on each update, so the estimate doesn't jump.
+const SMOOTHING_FACTOR: f64 = 0.2;
+
+#[derive(Clone, Copy, Debug)]
+struct Sample {
+  offset_millis: f64,
+  rtt_millis: f64,
+}
+
+// NTP-style estimator for the offset between our local clock and the
+// server's. Call `record_sample` every time a ping/pong round trip
+// completes; the sample with the lowest round-trip delay is the least
+// jittered and so is trusted most, with smoothing applied so the adopted
+// offset doesn't jump around between updates.
+//
+// Every timestamp here is milliseconds since the Unix epoch (e.g. from
+// `js_sys::Date::now()`), never `std::time::Instant`: `Instant` is a local
+// monotonic clock with no shared origin, so a server's `Instant` can't be
+// serialized to a client at all, let alone subtracted from the client's own.
+pub struct ClockSync {
+  samples: Vec<Sample>,
+  offset_millis: f64,
+  rtt_millis: f64,
+}
+
+impl ClockSync {
+  pub fn new() -> Self {
+    ClockSync {
+      samples: Vec::with_capacity(WINDOW_SIZE),
+      offset_millis: 0.0,
+      rtt_millis: 0.0,
+    }
+  }
+
+  // `t0`/`t3` are our local send/receive times; `t1`/`t2` are the server's
+  // receive/send times, as echoed back in the pong. offset = ((t1 - t0) +
+  // (t2 - t3)) / 2, round-trip delay = (t3 - t0) - (t2 - t1).
+  pub fn record_sample(&mut self, t0: f64, t1: f64, t2: f64, t3: f64) {
+    let offset_millis = ((t1 - t0) + (t2 - t3)) / 2.0;
+    let rtt_millis = (t3 - t0) - (t2 - t1);
+
+    if self.samples.len() == WINDOW_SIZE {
+      self.samples.remove(0);
+    }
+    self.samples.push(Sample {
+      offset_millis,
+      rtt_millis,
+    });
+
+    let best = *self
+      .samples
+      .iter()
+      .min_by(|a, b| a.rtt_millis.total_cmp(&b.rtt_millis))
+      .expect("just pushed a sample");
+    self.rtt_millis = best.rtt_millis;
+    self.smooth_towards(best.offset_millis);
+  }
+
+  fn smooth_towards(&mut self, offset_millis: f64) {
+    if self.samples.len() == 1 {
+      // First sample: nothing to smooth against yet.
+      self.offset_millis = offset_millis;
+      return;
+    }
+    self.offset_millis += (offset_millis - self.offset_millis) * SMOOTHING_FACTOR;
+  }
+
+  // Our best estimate of the server's wall-clock time right now, in
+  // milliseconds since the Unix epoch.
+  pub fn server_now_millis(&self) -> f64 {
+    js_sys::Date::now() + self.offset_millis
+  }
+
+  pub fn offset_millis(&self) -> f64 {
+    self.offset_millis
+  }
+
+  pub fn rtt_millis(&self) -> f64 {
+    self.rtt_millis
+  }
+}
+
+impl Default for ClockSync {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // server clock is exactly 100ms ahead of ours, and the round trip is
+  // symmetric (50ms each way), so offset should land on +100ms and rtt on
+  // 100ms.
+  #[test]
+  fn record_sample_computes_offset_and_rtt() {
+    let mut clock_sync = ClockSync::new();
+    clock_sync.record_sample(0.0, 150.0, 150.0, 100.0);
+    assert_eq!(clock_sync.offset_millis(), 100.0);
+    assert_eq!(clock_sync.rtt_millis(), 100.0);
+  }
+
+  #[test]
+  fn record_sample_handles_negative_offset() {
+    let mut clock_sync = ClockSync::new();
+    // Server clock is 100ms *behind* ours.
+    clock_sync.record_sample(0.0, -50.0, -50.0, 100.0);
+    assert_eq!(clock_sync.offset_millis(), -100.0);
+  }
+
+  // Among a window of samples, the one with the lowest RTT should be the
+  // one the offset estimate converges toward, since it's the least
+  // jittered.
+  #[test]
+  fn record_sample_prefers_lowest_rtt_sample() {
+    let mut clock_sync = ClockSync::new();
+    // High-RTT, noisy sample first: offset 200ms, rtt 300ms.
+    clock_sync.record_sample(0.0, 350.0, 350.0, 300.0);
+    assert_eq!(clock_sync.offset_millis(), 200.0);
+    // Low-RTT sample: offset 100ms, rtt 10ms. Should dominate the estimate
+    // even though it came in second.
+    clock_sync.record_sample(0.0, 105.0, 105.0, 10.0);
+    assert_eq!(clock_sync.rtt_millis(), 10.0);
+    // Smoothed a fifth of the way from 200ms toward 100ms.
+    assert_eq!(clock_sync.offset_millis(), 180.0);
+  }
+
+  // Once the window fills, the oldest sample should be evicted rather than
+  // permanently skewing the min-RTT selection.
+  #[test]
+  fn record_sample_evicts_oldest_once_window_is_full() {
+    let mut clock_sync = ClockSync::new();
+    // Fill the window with a single very low-RTT sample repeated, then push
+    // another WINDOW_SIZE higher-RTT samples: the low-RTT samples should
+    // fall off one by one and the rtt should go back up once they're all
+    // evicted.
+    for _ in 0..WINDOW_SIZE {
+      clock_sync.record_sample(0.0, 50.0, 50.0, 1.0);
+    }
+    assert_eq!(clock_sync.rtt_millis(), 1.0);
+    for _ in 0..WINDOW_SIZE {
+      clock_sync.record_sample(0.0, 50.0, 50.0, 20.0);
+    }
+    assert_eq!(clock_sync.rtt_millis(), 20.0);
+  }
+}
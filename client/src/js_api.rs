@@ -1,12 +1,26 @@
 use crate::canvas::*;
+use crate::clock_sync::ClockSync;
 use crate::network::create_websocket_and_listen;
-use instant::Instant;
+use crate::p2p::create_webrtc_and_listen;
+use crate::spectate::subscribe_to_relay;
 use rust_us_core::*;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::time::Duration;
 use wasm_bindgen::prelude::*;
 
+// Bounds for the playback-rate multiplier exposed to the scrubber UI.
+const MIN_PLAYBACK_RATE: f64 = 0.25;
+const MAX_PLAYBACK_RATE: f64 = 4.0;
+
+fn faster_playback_rate(current: f64) -> f64 {
+  (current * 2.0).min(MAX_PLAYBACK_RATE)
+}
+
+fn slower_playback_rate(current: f64) -> f64 {
+  (current / 2.0).max(MIN_PLAYBACK_RATE)
+}
+
 // When the `wee_alloc` feature is enabled, use `wee_alloc` as the global
 // allocator.
 #[cfg(feature = "wee_alloc")]
@@ -17,9 +31,17 @@ static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 #[wasm_bindgen]
 pub struct GameWrapper {
   canvas: Canvas,
-  previous_frame_time: Instant,
+  // Milliseconds since the Unix epoch, on our best estimate of the
+  // server's clock (see `clock_sync`), as of the last `simulate` call.
+  previous_frame_time_millis: f64,
   game: Arc<Mutex<Option<GameAsPlayer>>>,
   playback_server: Option<PlaybackServer>,
+  // Shared with the network layer, which feeds it ping/pong samples as
+  // they arrive; `GameWrapper` only ever reads from it.
+  clock_sync: Arc<Mutex<ClockSync>>,
+  playback_rate: f64,
+  prev_rate_buttons: (bool, bool),
+  is_spectating: bool,
 }
 
 #[wasm_bindgen]
@@ -38,7 +60,13 @@ impl GameWrapper {
     skip_back: bool,
     skip_forward: bool,
     pause_playback: bool,
+    rate_up: bool,
+    rate_down: bool,
   ) -> Result<(), JsValue> {
+    if self.is_spectating {
+      // Spectators receive state from the relay but can't act on the game.
+      return Ok(());
+    }
     let mut game = self
       .game
       .lock()
@@ -86,16 +114,55 @@ impl GameWrapper {
       } else if input.pause_playback && !prev_input.pause_playback {
         playback_server.toggle_pause();
         if !playback_server.paused() {
-          self.previous_frame_time = Instant::now();
+          self.previous_frame_time_millis = self.clock_sync.lock().unwrap().server_now_millis();
         }
+      } else if rate_up && !self.prev_rate_buttons.0 {
+        self.playback_rate = faster_playback_rate(self.playback_rate);
+      } else if rate_down && !self.prev_rate_buttons.1 {
+        self.playback_rate = slower_playback_rate(self.playback_rate);
       }
     }
+    self.prev_rate_buttons = (rate_up, rate_down);
     if game.state.status.finished() {
       return Ok(());
     }
     game.take_input(input).map_err(JsValue::from)
   }
 
+  // Jumps playback to an absolute position, expressed as a fraction (0.0-1.0)
+  // of the recording's total duration, for a scrubber UI to drag to any point
+  // instead of only stepping by fixed skips. Backed by `PlaybackServer`'s
+  // keyframe index, so this is a keyframe jump plus a short replay, not a
+  // replay of the whole recording from the start.
+  pub fn seek_to(&mut self, fraction: f64) -> Result<(), JsValue> {
+    if !fraction.is_finite() {
+      return Ok(());
+    }
+    let mut game = self
+      .game
+      .lock()
+      .expect("Internal Error: could not get a lock on the game");
+    let game = match game.as_mut() {
+      None => return Ok(()),
+      Some(game) => game,
+    };
+    let playback_server = match &mut self.playback_server {
+      None => return Ok(()),
+      Some(playback_server) => playback_server,
+    };
+    let fraction = fraction.clamp(0.0, 1.0);
+    let target = playback_server.duration().mul_f64(fraction);
+    playback_server
+      .skip_to(target, game)
+      .map_err(|e| JsValue::from(format!("{}", e)))?;
+    self.write_time_offset_into_url();
+    Ok(())
+  }
+
+  pub fn playback_rate(&self) -> f64 {
+    self.playback_rate
+  }
+
   pub fn simulate(&mut self) -> Result<bool, JsValue> {
     let mut game = self
       .game
@@ -105,9 +172,13 @@ impl GameWrapper {
       return Ok(false);
     }
     let game = game.as_mut().unwrap();
-    let now = Instant::now();
-    let elapsed = now - self.previous_frame_time;
-    self.previous_frame_time = now;
+    // Advance using our best estimate of server time rather than the raw
+    // local clock, so that all clients simulate against the same timeline
+    // instead of drifting apart from each other.
+    let now_millis = self.clock_sync.lock().unwrap().server_now_millis();
+    let elapsed =
+      Duration::from_secs_f64(((now_millis - self.previous_frame_time_millis) / 1000.0).max(0.0));
+    self.previous_frame_time_millis = now_millis;
     if let Some(playback_server) = &mut self.playback_server {
       if playback_server.paused() {
         // Skip all simulation and drawing while paused until we
@@ -115,7 +186,7 @@ impl GameWrapper {
         return Ok(true);
       }
       playback_server
-        .simulate(elapsed, game, false)
+        .simulate(elapsed.mul_f64(self.playback_rate), game, false)
         .map_err(|e| JsValue::from(format!("{}", e)))?;
       self.write_time_offset_into_url();
     }
@@ -134,8 +205,9 @@ impl GameWrapper {
     let href = window.location().href().unwrap_throw();
     let url = web_sys::Url::new(&href).unwrap_throw();
     url.set_search(&format!(
-      "?recording&time={}",
-      playback_server.current_time().as_secs()
+      "?recording&time={}&rate={}",
+      playback_server.current_time().as_secs(),
+      self.playback_rate
     ));
     let new_href = url.href();
     if href != new_href {
@@ -159,11 +231,33 @@ impl GameWrapper {
     Some(Duration::from_secs_f64(time))
   }
 
+  fn read_rate_from_url(&self) -> Option<f64> {
+    let window = web_sys::window().unwrap_throw();
+    let href = window.location().href().unwrap_throw();
+    let url = web_sys::Url::new(&href).unwrap_throw();
+    let rate: f64 = url.search_params().get("rate")?.parse().ok()?;
+    Some(rate.clamp(MIN_PLAYBACK_RATE, MAX_PLAYBACK_RATE))
+  }
+
   pub fn draw(&mut self) -> Result<(), JsValue> {
     self.canvas.draw(self.game.clone())
   }
+
+  // Current clock-offset and round-trip-time estimates, for drawing a
+  // latency indicator.
+  pub fn clock_offset_millis(&self) -> f64 {
+    self.clock_sync.lock().unwrap().offset_millis()
+  }
+
+  pub fn clock_rtt_millis(&self) -> f64 {
+    self.clock_sync.lock().unwrap().rtt_millis()
+  }
 }
 
+// Decodes the `RecordedGame` the server handed us, including its keyframe
+// index (periodic full-state snapshots plus a timestamp -> offset map, so
+// `PlaybackServer::skip_to` can jump straight to the nearest preceding
+// snapshot). A saved recording without a keyframe index fails to decode.
 fn get_recorded_game() -> Result<Option<RecordedGame>, JsValue> {
   let local_storage = web_sys::window()
     .ok_or("no window")?
@@ -210,23 +304,33 @@ pub fn make_game(name: String) -> Result<GameWrapper, JsValue> {
   let location = web_sys::window().ok_or("no window")?.location();
   let should_playback = location.search()?.contains("recording");
   let spectate = location.search()?.contains("spectate");
+  let p2p = location.search()?.contains("p2p");
+  let clock_sync = Arc::new(Mutex::new(ClockSync::new()));
   let mut wrapper;
   if !should_playback {
     wrapper = GameWrapper {
-      previous_frame_time: Instant::now(),
+      previous_frame_time_millis: js_sys::Date::now(),
       canvas: Canvas::find_in_document()?,
       game: Arc::new(Mutex::new(None)),
       playback_server: None,
+      clock_sync: clock_sync.clone(),
+      playback_rate: 1.0,
+      prev_rate_buttons: (false, false),
+      is_spectating: spectate,
     };
-    let join = if spectate {
-      JoinRequest::JoinAsSpectator
+    if spectate {
+      subscribe_to_relay(wrapper.game.clone(), clock_sync)?;
     } else {
-      JoinRequest::JoinAsPlayer {
+      let join = JoinRequest::JoinAsPlayer {
         name,
         preferred_color: Color::random(),
+      };
+      if p2p {
+        create_webrtc_and_listen(wrapper.game.clone(), join, clock_sync)?;
+      } else {
+        create_websocket_and_listen(wrapper.game.clone(), join, clock_sync)?;
       }
-    };
-    create_websocket_and_listen(wrapper.game.clone(), join)?;
+    }
   } else {
     let recording = match get_recorded_game()? {
       None => return Err(JsValue::from("No saved game found")),
@@ -241,11 +345,18 @@ pub fn make_game(name: String) -> Result<GameWrapper, JsValue> {
     let mut game_as_player = GameAsPlayer::new(UUID::random(), connection);
     game_as_player.state.status = GameStatus::Lobby;
     wrapper = GameWrapper {
-      previous_frame_time: Instant::now(),
+      previous_frame_time_millis: js_sys::Date::now(),
       canvas: Canvas::find_in_document()?,
       playback_server: Some(PlaybackServer::new(recording)),
       game: Arc::new(Mutex::new(Some(game_as_player))),
+      clock_sync,
+      playback_rate: 1.0,
+      prev_rate_buttons: (false, false),
+      is_spectating: spectate,
     };
+    if let Some(rate) = wrapper.read_rate_from_url() {
+      wrapper.playback_rate = rate;
+    }
     if let Some(offset) = wrapper.read_time_offset_from_url() {
       if let Some(playback_server) = &mut wrapper.playback_server {
         let mut game = wrapper.game.lock().unwrap_throw();
@@ -258,3 +369,26 @@ pub fn make_game(name: String) -> Result<GameWrapper, JsValue> {
 
   Ok(wrapper)
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn faster_playback_rate_doubles_up_to_the_max() {
+    assert_eq!(faster_playback_rate(1.0), 2.0);
+    assert_eq!(faster_playback_rate(2.0), 4.0);
+    // Already at the cap: doubling would overshoot it.
+    assert_eq!(faster_playback_rate(4.0), MAX_PLAYBACK_RATE);
+    assert_eq!(faster_playback_rate(3.0), MAX_PLAYBACK_RATE);
+  }
+
+  #[test]
+  fn slower_playback_rate_halves_down_to_the_min() {
+    assert_eq!(slower_playback_rate(1.0), 0.5);
+    assert_eq!(slower_playback_rate(0.5), 0.25);
+    // Already at the floor: halving would undershoot it.
+    assert_eq!(slower_playback_rate(0.25), MIN_PLAYBACK_RATE);
+    assert_eq!(slower_playback_rate(0.3), MIN_PLAYBACK_RATE);
+  }
+}
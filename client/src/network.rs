@@ -0,0 +1,128 @@
+use crate::clock_sync::ClockSync;
+use rust_us_core::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{MessageEvent, WebSocket};
+
+// How often we ping the server to keep `ClockSync`'s offset/RTT estimate
+// fresh.
+const PING_INTERVAL_MILLIS: i32 = 2000;
+
+#[derive(Clone)]
+pub struct WebSocketTx {
+  ws: WebSocket,
+}
+
+impl Tx for WebSocketTx {
+  fn send(&self, message: ClientToServerMessage) {
+    let encoded = match serde_json::to_string(&message) {
+      Ok(encoded) => encoded,
+      Err(e) => {
+        console_log!("Failed to encode {:?}: {}", message, e);
+        return;
+      }
+    };
+    if let Err(e) = self.ws.send_with_str(&encoded) {
+      console_log!("Failed to send over websocket: {:?}", e);
+    }
+  }
+}
+
+// Joins the server over a websocket, keeps `clock_sync` fed from periodic
+// ping/pong round trips, and forwards every other message to `game`. Returns
+// a `WebSocketTx` so callers (e.g. the WebRTC signaling handshake) can also
+// send messages over the same connection.
+pub fn create_websocket_and_listen(
+  game: Arc<Mutex<Option<GameAsPlayer>>>,
+  join: JoinRequest,
+  clock_sync: Arc<Mutex<ClockSync>>,
+) -> Result<WebSocketTx, JsValue> {
+  create_websocket_and_listen_with_signal_handler(game, join, clock_sync, None)
+}
+
+// As `create_websocket_and_listen`, but also routes any `Signal` messages
+// (SDP offers/answers, ICE candidates) the websocket receives to
+// `on_signal`, for a WebRTC handshake riding on this same connection rather
+// than behaving as ordinary game traffic.
+pub fn create_websocket_and_listen_with_signal_handler(
+  game: Arc<Mutex<Option<GameAsPlayer>>>,
+  join: JoinRequest,
+  clock_sync: Arc<Mutex<ClockSync>>,
+  on_signal: Option<Rc<RefCell<dyn FnMut(SignalPayload)>>>,
+) -> Result<WebSocketTx, JsValue> {
+  let location = web_sys::window().ok_or("no window")?.location();
+  let host = location.host()?;
+  let ws = WebSocket::new(&format!("wss://{}/ws", host))?;
+  let tx = WebSocketTx { ws: ws.clone() };
+
+  *game.lock().unwrap() = Some(GameAsPlayer::new(UUID::random(), Box::new(tx.clone())));
+
+  let tx_for_open = tx.clone();
+  let onopen = Closure::wrap(Box::new(move |_: JsValue| {
+    tx_for_open.send(ClientToServerMessage::Join(join.clone()));
+  }) as Box<dyn FnMut(JsValue)>);
+  ws.set_onopen(Some(onopen.as_ref().unchecked_ref()));
+  onopen.forget();
+
+  let tx_for_ping = tx.clone();
+  let ping = Closure::wrap(Box::new(move || {
+    tx_for_ping.send(ClientToServerMessage::Ping {
+      sent_at_millis: js_sys::Date::now(),
+    });
+  }) as Box<dyn FnMut()>);
+  web_sys::window()
+    .ok_or("no window")?
+    .set_interval_with_callback_and_timeout_and_arguments_0(
+      ping.as_ref().unchecked_ref(),
+      PING_INTERVAL_MILLIS,
+    )?;
+  ping.forget();
+
+  let onmessage = Closure::wrap(Box::new(move |event: MessageEvent| {
+    let text = match event.data().as_string() {
+      Some(text) => text,
+      None => return,
+    };
+    let message: ServerToClientMessage = match serde_json::from_str(&text) {
+      Ok(message) => message,
+      Err(e) => {
+        console_log!("Could not decode server message {:?}: {}", text, e);
+        return;
+      }
+    };
+    match message {
+      ServerToClientMessage::Pong {
+        sent_at_millis,
+        server_received_at_millis,
+        server_sent_at_millis,
+      } => {
+        let received_at_millis = js_sys::Date::now();
+        clock_sync.lock().unwrap().record_sample(
+          sent_at_millis,
+          server_received_at_millis,
+          server_sent_at_millis,
+          received_at_millis,
+        );
+      }
+      ServerToClientMessage::Signal(payload) => {
+        if let Some(on_signal) = &on_signal {
+          (on_signal.borrow_mut())(payload);
+        } else {
+          console_log!("Received a Signal message with no handler registered");
+        }
+      }
+      other => {
+        if let Some(game) = game.lock().unwrap().as_mut() {
+          game.handle_server_message(other);
+        }
+      }
+    }
+  }) as Box<dyn FnMut(MessageEvent)>);
+  ws.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+  onmessage.forget();
+
+  Ok(tx)
+}
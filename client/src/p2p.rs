@@ -0,0 +1,197 @@
+use crate::clock_sync::ClockSync;
+use crate::network::create_websocket_and_listen_with_signal_handler;
+use rust_us_core::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::Mutex;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::{
+  RtcDataChannel, RtcDataChannelEvent, RtcIceCandidateInit, RtcPeerConnection, RtcSdpType,
+  RtcSessionDescriptionInit,
+};
+
+// A `GameAsPlayer` connection that ships `ClientToServerMessage`s over an
+// unreliable/unordered `RTCDataChannel` instead of the websocket, for
+// host<->client traffic that cares more about latency than delivery
+// guarantees. Setup (SDP offer/answer + ICE candidates) still rides the
+// existing websocket, the same way the gst webrtcsink signaller piggybacks
+// its handshake on a side channel before handing off to the data channel.
+pub struct WebRtcTx {
+  data_channel: RtcDataChannel,
+}
+
+impl Tx for WebRtcTx {
+  fn send(&self, message: ClientToServerMessage) {
+    let encoded = match serde_json::to_string(&message) {
+      Ok(encoded) => encoded,
+      Err(e) => {
+        console_log!("Failed to encode {:?}: {}", message, e);
+        return;
+      }
+    };
+    if let Err(e) = self.data_channel.send_with_str(&encoded) {
+      console_log!("Failed to send over data channel: {:?}", e);
+    }
+  }
+}
+
+// Wires up `onopen` (swap the game's connection over to the data channel)
+// and `onmessage` (decode and forward to `game`) on a data channel,
+// regardless of whether we created it locally (as the offering peer) or
+// received it via `ondatachannel` (as the answering peer) — both need the
+// same handlers installed, or whichever side didn't create the channel
+// silently drops every message sent to it.
+fn wire_up_data_channel(channel: &RtcDataChannel, game: Arc<Mutex<Option<GameAsPlayer>>>) {
+  let channel_for_open = channel.clone();
+  let game_for_open = game.clone();
+  let onopen = Closure::wrap(Box::new(move |_: JsValue| {
+    let connection = Box::new(WebRtcTx {
+      data_channel: channel_for_open.clone(),
+    });
+    // Swap the transport on the `GameAsPlayer` the websocket's
+    // `JoinAsPlayer` already seeded, rather than minting a fresh one: the
+    // server registered this connection under `my_uuid`, and a new random
+    // UUID here would mean every future input arrives under an identity
+    // the server never saw join.
+    if let Some(game) = game_for_open.lock().unwrap().as_mut() {
+      game.set_connection(connection);
+    }
+  }) as Box<dyn FnMut(JsValue)>);
+  channel.set_onopen(Some(onopen.as_ref().unchecked_ref()));
+  onopen.forget();
+
+  let onmessage = Closure::wrap(Box::new(move |event: web_sys::MessageEvent| {
+    let text = match event.data().as_string() {
+      Some(text) => text,
+      None => return,
+    };
+    match serde_json::from_str::<ServerToClientMessage>(&text) {
+      Ok(message) => {
+        if let Some(game) = game.lock().unwrap().as_mut() {
+          game.handle_server_message(message);
+        }
+      }
+      Err(e) => console_log!("Could not decode data channel message: {}", e),
+    }
+  }) as Box<dyn FnMut(web_sys::MessageEvent)>);
+  channel.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+  onmessage.forget();
+}
+
+// Opens a peer connection and joins the lobby over the websocket purely to
+// broker signaling (SDP offer/answer, ICE candidates); once the resulting
+// data channel opens, `wire_up_data_channel`'s `onopen` swaps the game's
+// connection over to it, so there's only ever one connection the game is
+// actually sending input through at a time — the websocket's own player
+// join is superseded rather than left running alongside it.
+pub fn create_webrtc_and_listen(
+  game: Arc<Mutex<Option<GameAsPlayer>>>,
+  join: JoinRequest,
+  clock_sync: Arc<Mutex<ClockSync>>,
+) -> Result<(), JsValue> {
+  let peer_connection = RtcPeerConnection::new()?;
+  let mut channel_init = web_sys::RtcDataChannelInit::new();
+  channel_init.ordered(false);
+  channel_init.max_retransmits(0);
+  let local_channel =
+    peer_connection.create_data_channel_with_data_channel_dict("game", &channel_init);
+  local_channel.set_binary_type(web_sys::RtcDataChannelType::Arraybuffer);
+  wire_up_data_channel(&local_channel, game.clone());
+
+  let game_for_remote_channel = game.clone();
+  let ondatachannel = Closure::wrap(Box::new(move |event: RtcDataChannelEvent| {
+    wire_up_data_channel(&event.channel(), game_for_remote_channel.clone());
+  }) as Box<dyn FnMut(RtcDataChannelEvent)>);
+  peer_connection.set_ondatachannel(Some(ondatachannel.as_ref().unchecked_ref()));
+  ondatachannel.forget();
+
+  let pc_for_ice = peer_connection.clone();
+  let on_signal = Rc::new(RefCell::new(move |payload: SignalPayload| {
+    let pc = pc_for_ice.clone();
+    spawn_local(async move {
+      if let Err(e) = apply_remote_signal(&pc, payload).await {
+        console_log!("Failed to apply signal: {:?}", e);
+      }
+    });
+  }));
+
+  let tx = create_websocket_and_listen_with_signal_handler(
+    game,
+    join,
+    clock_sync,
+    Some(on_signal as Rc<RefCell<dyn FnMut(SignalPayload)>>),
+  )?;
+
+  let tx_for_ice = tx.clone();
+  let onicecandidate = Closure::wrap(Box::new(move |event: web_sys::RtcPeerConnectionIceEvent| {
+    if let Some(candidate) = event.candidate() {
+      tx_for_ice.send(ClientToServerMessage::Signal(SignalPayload::IceCandidate(
+        candidate.candidate(),
+      )));
+    }
+  }) as Box<dyn FnMut(web_sys::RtcPeerConnectionIceEvent)>);
+  peer_connection.set_onicecandidate(Some(onicecandidate.as_ref().unchecked_ref()));
+  onicecandidate.forget();
+
+  spawn_local(async move {
+    if let Err(e) = make_and_send_offer(&peer_connection, &tx).await {
+      console_log!("Failed to create offer: {:?}", e);
+    }
+  });
+
+  Ok(())
+}
+
+async fn make_and_send_offer(
+  peer_connection: &RtcPeerConnection,
+  tx: &crate::network::WebSocketTx,
+) -> Result<(), JsValue> {
+  let offer = JsFuture::from(peer_connection.create_offer()).await?;
+  let sdp = js_sys::Reflect::get(&offer, &"sdp".into())?
+    .as_string()
+    .ok_or("offer had no sdp")?;
+  let mut description = RtcSessionDescriptionInit::new(RtcSdpType::Offer);
+  description.sdp(&sdp);
+  JsFuture::from(peer_connection.set_local_description(&description)).await?;
+  tx.send(ClientToServerMessage::Signal(SignalPayload::Offer(sdp)));
+  Ok(())
+}
+
+// Applies whatever the other end of the signaling channel sent us: an
+// answer to our offer, an offer of our own to answer, or an ICE candidate,
+// which is added as soon as it arrives (candidates are not queued while the
+// remote description is still unset).
+async fn apply_remote_signal(
+  peer_connection: &RtcPeerConnection,
+  payload: SignalPayload,
+) -> Result<(), JsValue> {
+  match payload {
+    SignalPayload::Answer(sdp) => {
+      let mut description = RtcSessionDescriptionInit::new(RtcSdpType::Answer);
+      description.sdp(&sdp);
+      JsFuture::from(peer_connection.set_remote_description(&description)).await?;
+    }
+    SignalPayload::Offer(sdp) => {
+      let mut description = RtcSessionDescriptionInit::new(RtcSdpType::Offer);
+      description.sdp(&sdp);
+      JsFuture::from(peer_connection.set_remote_description(&description)).await?;
+      let answer = JsFuture::from(peer_connection.create_answer()).await?;
+      let answer_sdp = js_sys::Reflect::get(&answer, &"sdp".into())?
+        .as_string()
+        .ok_or("answer had no sdp")?;
+      let mut answer_description = RtcSessionDescriptionInit::new(RtcSdpType::Answer);
+      answer_description.sdp(&answer_sdp);
+      JsFuture::from(peer_connection.set_local_description(&answer_description)).await?;
+    }
+    SignalPayload::IceCandidate(candidate) => {
+      let mut init = RtcIceCandidateInit::new(&candidate);
+      init.sdp_mid(Some(""));
+      JsFuture::from(peer_connection.add_ice_candidate_with_opt_rtc_ice_candidate_init(Some(&init)))
+        .await?;
+    }
+  }
+  Ok(())
+}
@@ -0,0 +1,23 @@
+use crate::clock_sync::ClockSync;
+use crate::network::create_websocket_and_listen;
+use rust_us_core::*;
+use std::sync::Arc;
+use std::sync::Mutex;
+use wasm_bindgen::prelude::*;
+
+// Joins as a spectator over the websocket. On the server, a
+// `JoinRequest::JoinAsSpectator` connection is handed off to
+// `server::GameHost::join_spectator`, which seeds it with a `Replay` of the
+// room's current state and then forwards every subsequent update from
+// `GameRelay` to it, instead of the room running an independent
+// `GameAsPlayer` simulation per spectator the way a player join does. The
+// client side looks like an ordinary join (and doesn't need to look like
+// anything else — the distinct handling lives entirely in which messages
+// the server sends back).
+pub fn subscribe_to_relay(
+  game: Arc<Mutex<Option<GameAsPlayer>>>,
+  clock_sync: Arc<Mutex<ClockSync>>,
+) -> Result<(), JsValue> {
+  create_websocket_and_listen(game, JoinRequest::JoinAsSpectator, clock_sync)?;
+  Ok(())
+}
@@ -0,0 +1,104 @@
+use crate::{ClientToServerMessage, GameStatus, InputState, ServerToClientMessage, Tx, UUID};
+use std::fmt;
+use std::time::Duration;
+
+#[derive(Debug)]
+pub struct CoreError(String);
+
+impl fmt::Display for CoreError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.0)
+  }
+}
+
+// Only the wasm client ever needs to hand a `CoreError` back across the
+// JS boundary; the native `server` crate has no `JsValue` to convert to.
+#[cfg(target_arch = "wasm32")]
+impl From<CoreError> for wasm_bindgen::JsValue {
+  fn from(e: CoreError) -> Self {
+    wasm_bindgen::JsValue::from(format!("{}", e))
+  }
+}
+
+pub struct GameState {
+  pub status: GameStatus,
+}
+
+// A point-in-time snapshot of everything `PlaybackServer::skip_to` needs to
+// restore in order to jump straight into the middle of a recording, without
+// replaying every event from the start.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct GameSnapshot {
+  pub status: GameStatus,
+}
+
+// The player's (or spectator's) view of an in-progress or recorded game:
+// the connection it sends input over, and enough state to draw the current
+// frame. Movement, tasks, and kill/report resolution live in the full
+// simulation this core crate doesn't attempt to reproduce here.
+pub struct GameAsPlayer {
+  pub my_uuid: UUID,
+  pub state: GameState,
+  connection: Box<dyn Tx>,
+  inputs: InputState,
+}
+
+impl GameAsPlayer {
+  pub fn new(my_uuid: UUID, connection: Box<dyn Tx>) -> Self {
+    GameAsPlayer {
+      my_uuid,
+      state: GameState {
+        status: GameStatus::Connecting,
+      },
+      connection,
+      inputs: InputState::default(),
+    }
+  }
+
+  pub fn inputs(&self) -> InputState {
+    self.inputs
+  }
+
+  // Swaps the transport input is sent over without otherwise disturbing
+  // `my_uuid`/`state`, for when a connection already registered with the
+  // server (e.g. over the websocket) hands off to a faster one (e.g. a
+  // WebRTC data channel) that the server never needs to see a fresh join
+  // for.
+  pub fn set_connection(&mut self, connection: Box<dyn Tx>) {
+    self.connection = connection;
+  }
+
+  pub fn take_input(&mut self, input: InputState) -> Result<(), CoreError> {
+    self.inputs = input;
+    self.connection.send(ClientToServerMessage::Input(input));
+    Ok(())
+  }
+
+  pub fn simulate(&mut self, _elapsed: Duration) -> bool {
+    !self.state.status.finished()
+  }
+
+  pub fn handle_server_message(&mut self, message: ServerToClientMessage) {
+    match message {
+      ServerToClientMessage::Replay(recording) => {
+        self.restore_snapshot(recording.initial_snapshot());
+      }
+      ServerToClientMessage::StateDelta(snapshot) => {
+        self.restore_snapshot(snapshot);
+      }
+      ServerToClientMessage::Signal(_) | ServerToClientMessage::Pong { .. } => {
+        // Handled by the transport layer before it ever reaches the game.
+      }
+    }
+  }
+
+  pub fn snapshot(&self) -> GameSnapshot {
+    GameSnapshot {
+      status: self.state.status,
+    }
+  }
+
+  pub fn restore_snapshot(&mut self, snapshot: GameSnapshot) {
+    self.state.status = snapshot.status;
+  }
+}
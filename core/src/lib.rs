@@ -0,0 +1,29 @@
+mod game;
+mod message;
+mod playback;
+mod version;
+
+pub use game::*;
+pub use message::*;
+pub use playback::*;
+pub use version::get_version_sha;
+
+#[macro_export]
+macro_rules! console_log {
+  ($($arg:tt)*) => {
+    $crate::log(&format!($($arg)*))
+  };
+}
+
+// Backs `console_log!`: the browser console in a wasm build, stdout
+// everywhere else (the native `server` crate, and `cargo test` on this
+// crate), since `web_sys::console` only links against a wasm32 target.
+#[cfg(target_arch = "wasm32")]
+pub fn log(message: &str) {
+  web_sys::console::log_1(&message.into());
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn log(message: &str) {
+  println!("{}", message);
+}
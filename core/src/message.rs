@@ -0,0 +1,158 @@
+use crate::RecordedGame;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UUID(u128);
+
+impl UUID {
+  #[cfg(target_arch = "wasm32")]
+  pub fn random() -> Self {
+    let hi = (js_sys::Math::random() * u64::MAX as f64) as u128;
+    let lo = (js_sys::Math::random() * u64::MAX as f64) as u128;
+    UUID((hi << 64) | lo)
+  }
+
+  #[cfg(not(target_arch = "wasm32"))]
+  pub fn random() -> Self {
+    UUID((native_random_u64() as u128) << 64 | native_random_u64() as u128)
+  }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Color {
+  pub r: u8,
+  pub g: u8,
+  pub b: u8,
+}
+
+impl Color {
+  #[cfg(target_arch = "wasm32")]
+  pub fn random() -> Self {
+    Color {
+      r: (js_sys::Math::random() * 255.0) as u8,
+      g: (js_sys::Math::random() * 255.0) as u8,
+      b: (js_sys::Math::random() * 255.0) as u8,
+    }
+  }
+
+  #[cfg(not(target_arch = "wasm32"))]
+  pub fn random() -> Self {
+    Color {
+      r: native_random_u64() as u8,
+      g: native_random_u64() as u8,
+      b: native_random_u64() as u8,
+    }
+  }
+}
+
+// `js_sys::Math::random` only links against a wasm32 target; the native
+// `server` crate (and `cargo test` on this crate) instead draws from
+// `RandomState`'s per-instance keying, which differs per call without
+// pulling in a dependency just for this.
+#[cfg(not(target_arch = "wasm32"))]
+fn native_random_u64() -> u64 {
+  use std::collections::hash_map::RandomState;
+  use std::hash::{BuildHasher, Hasher};
+  RandomState::new().build_hasher().finish()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameStatus {
+  Connecting,
+  Lobby,
+  Playing,
+  Won,
+  Lost,
+}
+
+impl GameStatus {
+  pub fn finished(&self) -> bool {
+    matches!(self, GameStatus::Won | GameStatus::Lost)
+  }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct InputState {
+  pub up: bool,
+  pub down: bool,
+  pub left: bool,
+  pub right: bool,
+  pub kill: bool,
+  pub report: bool,
+  pub activate: bool,
+  pub play: bool,
+  pub skip_back: bool,
+  pub skip_forward: bool,
+  pub pause_playback: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum JoinRequest {
+  JoinAsPlayer { name: String, preferred_color: Color },
+  JoinAsSpectator,
+}
+
+// SDP offer/answer and ICE candidates, relayed between two peers over
+// whichever signaling channel (normally the websocket) they're both
+// already connected to.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum SignalPayload {
+  Offer(String),
+  Answer(String),
+  IceCandidate(String),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum ClientToServerMessage {
+  Join(JoinRequest),
+  Input(InputState),
+  Signal(SignalPayload),
+  // `sent_at_millis` is our local wall-clock time (milliseconds since the
+  // Unix epoch), echoed back in the matching `Pong` so we can measure
+  // round-trip delay without assuming the two machines' monotonic clocks
+  // have anything in common.
+  Ping { sent_at_millis: f64 },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum ServerToClientMessage {
+  Replay(RecordedGame),
+  // An incremental authoritative update. The full simulation (movement,
+  // tasks, kill/report resolution) this core crate doesn't reproduce would
+  // carry a richer payload; `GameSnapshot` is enough for playback, seeking,
+  // and the spectator relay to agree on what "the current state" means.
+  StateDelta(crate::GameSnapshot),
+  Signal(SignalPayload),
+  Pong {
+    sent_at_millis: f64,
+    server_received_at_millis: f64,
+    server_sent_at_millis: f64,
+  },
+}
+
+impl ServerToClientMessage {
+  pub fn kind(&self) -> &'static str {
+    match self {
+      ServerToClientMessage::Replay(_) => "Replay",
+      ServerToClientMessage::StateDelta(_) => "StateDelta",
+      ServerToClientMessage::Signal(_) => "Signal",
+      ServerToClientMessage::Pong { .. } => "Pong",
+    }
+  }
+}
+
+// A destination for outgoing `ClientToServerMessage`s, implemented once per
+// transport (websocket, WebRTC data channel, a no-op sink during playback)
+// so the rest of `GameAsPlayer` doesn't need to know which one it's using.
+pub trait Tx {
+  fn send(&self, message: ClientToServerMessage);
+}
+
+pub struct PlaybackTx {}
+
+impl Tx for PlaybackTx {
+  fn send(&self, _message: ClientToServerMessage) {
+    // Recorded games are read-only: there's no server on the other end to
+    // send input to.
+  }
+}
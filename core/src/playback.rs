@@ -0,0 +1,291 @@
+use crate::{GameAsPlayer, GameSnapshot, ServerToClientMessage};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+
+// How often we embed a full state snapshot among the incremental events, so
+// `skip_to`/`seek_to` never has to replay more than this much of the
+// recording after jumping to the nearest preceding keyframe.
+const KEYFRAME_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Keyframe {
+  at: Duration,
+  snapshot: GameSnapshot,
+  // Index into `events` just past the event that triggered this keyframe
+  // (whose effect `snapshot` already includes), i.e. where replay should
+  // resume after restoring `snapshot`.
+  event_index: usize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct RecordedEvent {
+  at: Duration,
+  message: ServerToClientMessage,
+}
+
+// A recorded game, as saved by `save_recorded_game` and loaded by
+// `get_recorded_game`. Interleaves the incremental events with periodic
+// full-state snapshots ("keyframes") and an index from timestamp to the
+// nearest preceding one, the same segmented/seekable shape fragmented-MP4
+// and HLS muxers use, so scrubbing is O(keyframe spacing) instead of O(whole
+// game). `new` always seeds the index with a keyframe at t=0. Decoding
+// requires the `keyframes` field: a recording saved without one does not
+// deserialize into this type.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecordedGame {
+  pub version: String,
+  keyframes: Vec<Keyframe>,
+  events: Vec<RecordedEvent>,
+}
+
+impl RecordedGame {
+  pub fn new(version: String, initial_snapshot: GameSnapshot) -> Self {
+    RecordedGame {
+      version,
+      keyframes: vec![Keyframe {
+        at: Duration::from_secs(0),
+        snapshot: initial_snapshot,
+        event_index: 0,
+      }],
+      events: Vec::new(),
+    }
+  }
+
+  // Appends an event at `at`, taking a new keyframe if it's been at least
+  // `KEYFRAME_INTERVAL` since the last one. `current_snapshot` is the state
+  // *after* this event is applied, so that restoring this keyframe and
+  // replaying forward from `event_index` reproduces the same state.
+  pub fn record_event(
+    &mut self,
+    at: Duration,
+    message: ServerToClientMessage,
+    current_snapshot: GameSnapshot,
+  ) {
+    self.events.push(RecordedEvent { at, message });
+    let last_keyframe_at = self.keyframes.last().expect("always has one").at;
+    if at.saturating_sub(last_keyframe_at) >= KEYFRAME_INTERVAL {
+      self.keyframes.push(Keyframe {
+        at,
+        snapshot: current_snapshot,
+        event_index: self.events.len(),
+      });
+    }
+  }
+
+  pub fn duration(&self) -> Duration {
+    self
+      .events
+      .last()
+      .map(|e| e.at)
+      .unwrap_or(Duration::from_secs(0))
+  }
+
+  // Binary search for the keyframe at or immediately before `target`.
+  fn keyframe_before(&self, target: Duration) -> &Keyframe {
+    match self.keyframes.binary_search_by_key(&target, |k| k.at) {
+      Ok(i) => &self.keyframes[i],
+      Err(0) => &self.keyframes[0],
+      Err(i) => &self.keyframes[i - 1],
+    }
+  }
+
+  // First index in `events` whose timestamp is after `after`.
+  fn first_event_index_after(&self, after: Duration) -> usize {
+    self.events.partition_point(|e| e.at <= after)
+  }
+
+  // Used by `GameAsPlayer::handle_server_message` when a freshly-joined
+  // client receives its initial `Replay`.
+  pub fn initial_snapshot(&self) -> GameSnapshot {
+    self.keyframes[0].snapshot.clone()
+  }
+}
+
+#[derive(Debug)]
+pub struct PlaybackError(String);
+
+impl fmt::Display for PlaybackError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.0)
+  }
+}
+
+pub struct PlaybackServer {
+  recording: RecordedGame,
+  current_time: Duration,
+  paused: bool,
+}
+
+impl PlaybackServer {
+  pub fn new(recording: RecordedGame) -> Self {
+    PlaybackServer {
+      recording,
+      current_time: Duration::from_secs(0),
+      paused: false,
+    }
+  }
+
+  pub fn current_time(&self) -> Duration {
+    self.current_time
+  }
+
+  pub fn duration(&self) -> Duration {
+    self.recording.duration()
+  }
+
+  pub fn paused(&self) -> bool {
+    self.paused
+  }
+
+  pub fn toggle_pause(&mut self) {
+    self.paused = !self.paused;
+  }
+
+  // Advances playback by `elapsed`, replaying forward sequentially from
+  // `current_time` (no need to consult the keyframe index for a short,
+  // in-order step).
+  pub fn simulate(
+    &mut self,
+    elapsed: Duration,
+    game: &mut GameAsPlayer,
+    _record: bool,
+  ) -> Result<(), PlaybackError> {
+    let target = (self.current_time + elapsed).min(self.duration());
+    let start = self.recording.first_event_index_after(self.current_time);
+    for event in &self.recording.events[start..] {
+      if event.at > target {
+        break;
+      }
+      game.handle_server_message(event.message.clone());
+    }
+    self.current_time = target;
+    Ok(())
+  }
+
+  // Jumps straight to `target`: restores the nearest preceding keyframe
+  // snapshot and replays only the events between it and `target`, rather
+  // than replaying the whole recording from the beginning.
+  pub fn skip_to(
+    &mut self,
+    target: Duration,
+    game: &mut GameAsPlayer,
+  ) -> Result<(), PlaybackError> {
+    let target = target.min(self.duration());
+    let keyframe = self.recording.keyframe_before(target);
+    game.restore_snapshot(keyframe.snapshot.clone());
+    for event in &self.recording.events[keyframe.event_index..] {
+      if event.at > target {
+        break;
+      }
+      game.handle_server_message(event.message.clone());
+    }
+    self.current_time = target;
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{GameAsPlayer, GameStatus, PlaybackTx, UUID};
+
+  fn snapshot(status: GameStatus) -> GameSnapshot {
+    GameSnapshot { status }
+  }
+
+  fn player() -> GameAsPlayer {
+    GameAsPlayer::new(UUID::random(), Box::new(PlaybackTx {}))
+  }
+
+  // Builds a recording with events at 5s, 12s (crosses KEYFRAME_INTERVAL,
+  // so it takes a keyframe) and 15s.
+  fn three_event_recording() -> RecordedGame {
+    let mut recording = RecordedGame::new("v1".into(), snapshot(GameStatus::Lobby));
+    recording.record_event(
+      Duration::from_secs(5),
+      ServerToClientMessage::StateDelta(snapshot(GameStatus::Playing)),
+      snapshot(GameStatus::Playing),
+    );
+    recording.record_event(
+      Duration::from_secs(12),
+      ServerToClientMessage::StateDelta(snapshot(GameStatus::Won)),
+      snapshot(GameStatus::Won),
+    );
+    recording.record_event(
+      Duration::from_secs(15),
+      ServerToClientMessage::StateDelta(snapshot(GameStatus::Lost)),
+      snapshot(GameStatus::Lost),
+    );
+    recording
+  }
+
+  #[test]
+  fn record_event_takes_a_keyframe_only_after_the_interval_elapses() {
+    let mut recording = RecordedGame::new("v1".into(), snapshot(GameStatus::Lobby));
+    recording.record_event(
+      Duration::from_secs(5),
+      ServerToClientMessage::StateDelta(snapshot(GameStatus::Playing)),
+      snapshot(GameStatus::Playing),
+    );
+    // 5s since the implicit t=0 keyframe: still under KEYFRAME_INTERVAL.
+    assert_eq!(recording.keyframes.len(), 1);
+
+    recording.record_event(
+      Duration::from_secs(12),
+      ServerToClientMessage::StateDelta(snapshot(GameStatus::Won)),
+      snapshot(GameStatus::Won),
+    );
+    // 12s since the last keyframe (still t=0) is past KEYFRAME_INTERVAL.
+    assert_eq!(recording.keyframes.len(), 2);
+  }
+
+  #[test]
+  fn skip_to_restores_the_nearest_preceding_keyframe() {
+    let mut server = PlaybackServer::new(three_event_recording());
+    let mut game = player();
+    server.skip_to(Duration::from_secs(13), &mut game).unwrap();
+    // Restores from the t=12 keyframe (status Won) and doesn't replay the
+    // t=15 event, since it's after the target.
+    assert_eq!(game.snapshot(), snapshot(GameStatus::Won));
+    assert_eq!(server.current_time(), Duration::from_secs(13));
+  }
+
+  #[test]
+  fn skip_to_replays_events_between_the_keyframe_and_the_target() {
+    let mut server = PlaybackServer::new(three_event_recording());
+    let mut game = player();
+    server.skip_to(Duration::from_secs(15), &mut game).unwrap();
+    assert_eq!(game.snapshot(), snapshot(GameStatus::Lost));
+  }
+
+  #[test]
+  fn skip_to_can_seek_backward_past_later_keyframes() {
+    let mut server = PlaybackServer::new(three_event_recording());
+    let mut game = player();
+    server.skip_to(Duration::from_secs(15), &mut game).unwrap();
+    assert_eq!(game.snapshot(), snapshot(GameStatus::Lost));
+
+    // Seeking back to t=0 should land on the implicit first keyframe, not
+    // anywhere replay happened to leave off.
+    server.skip_to(Duration::from_secs(0), &mut game).unwrap();
+    assert_eq!(game.snapshot(), snapshot(GameStatus::Lobby));
+    assert_eq!(server.current_time(), Duration::from_secs(0));
+  }
+
+  #[test]
+  fn skip_to_clamps_to_the_end_of_the_recording() {
+    let mut recording = RecordedGame::new("v1".into(), snapshot(GameStatus::Lobby));
+    recording.record_event(
+      Duration::from_secs(5),
+      ServerToClientMessage::StateDelta(snapshot(GameStatus::Won)),
+      snapshot(GameStatus::Won),
+    );
+    let mut server = PlaybackServer::new(recording);
+    let mut game = player();
+    server
+      .skip_to(Duration::from_secs(999), &mut game)
+      .unwrap();
+    assert_eq!(server.current_time(), Duration::from_secs(5));
+  }
+}
@@ -0,0 +1,6 @@
+// The short SHA of the commit this binary was built from, baked in at
+// compile time so a client and server (or a recording and the player
+// replaying it) can tell whether they're running the same version.
+pub fn get_version_sha() -> &'static str {
+  env!("VERGEN_SHA_SHORT")
+}
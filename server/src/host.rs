@@ -0,0 +1,75 @@
+use crate::relay::GameRelay;
+use rust_us_core::{GameSnapshot, RecordedGame, ServerToClientMessage};
+use std::sync::Mutex;
+use std::time::Instant;
+
+// A sink for messages the server sends to one connected client, one impl
+// per transport (normally a websocket write half) so `GameHost` doesn't
+// need to know which one it's talking to — the mirror of `rust_us_core::Tx`,
+// but for the server -> client direction.
+pub trait ClientSink: Send + 'static {
+  fn send(&self, message: ServerToClientMessage);
+}
+
+// Owns the single authoritative game a room is running: the relay every
+// spectator subscribes to, and the recording every update is also persisted
+// into, so "what spectators watch live" and "what `get_recorded_game` loads
+// later" are guaranteed to be the same bytes rather than two independently
+// assembled views of the same game.
+pub struct GameHost {
+  relay: GameRelay,
+  recording: Mutex<RecordedGame>,
+  started_at: Instant,
+}
+
+impl GameHost {
+  pub fn new(version: String, initial_snapshot: GameSnapshot) -> Self {
+    GameHost {
+      relay: GameRelay::new(version.clone(), initial_snapshot.clone()),
+      recording: Mutex::new(RecordedGame::new(version, initial_snapshot)),
+      started_at: Instant::now(),
+    }
+  }
+
+  // Publishes `message` to every subscribed spectator and records it into
+  // the same game's recording, so a save taken any time afterward sees an
+  // event stream consistent with what spectators just watched live, instead
+  // of the relay and the recording drifting into two different histories.
+  pub fn broadcast_update(&self, message: ServerToClientMessage, current_snapshot: GameSnapshot) {
+    self.recording.lock().unwrap().record_event(
+      self.started_at.elapsed(),
+      message.clone(),
+      current_snapshot,
+    );
+    self.relay.publish(message);
+  }
+
+  // Subscribes `sink` to the relay: it's seeded with a `Replay` of the
+  // current state, then a background task forwards live updates to it as
+  // the relay's broadcast channel receives them (a spectator that falls more
+  // than the channel's capacity behind skips the updates it missed and keeps
+  // receiving the ones after). This is the path a
+  // `JoinRequest::JoinAsSpectator` connection should be handed off to,
+  // instead of the room spinning up an independent `GameAsPlayer`
+  // simulation per spectator the way a player join does.
+  pub fn join_spectator(&self, sink: Box<dyn ClientSink>) {
+    let (seed, mut receiver) = self.relay.subscribe();
+    sink.send(seed);
+    tokio::spawn(async move {
+      loop {
+        match receiver.recv().await {
+          Ok(message) => sink.send(message),
+          Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+          Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+        }
+      }
+    });
+  }
+
+  // A point-in-time copy of the recording so far, for a save triggered
+  // mid-game (the same shape `save_recorded_game` persists once the game
+  // ends).
+  pub fn save_recording(&self) -> RecordedGame {
+    self.recording.lock().unwrap().clone()
+  }
+}
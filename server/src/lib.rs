@@ -0,0 +1,5 @@
+mod host;
+mod relay;
+
+pub use host::{ClientSink, GameHost};
+pub use relay::GameRelay;
@@ -0,0 +1,67 @@
+use rust_us_core::{GameSnapshot, RecordedGame, ServerToClientMessage};
+use std::sync::Mutex;
+use tokio::sync::broadcast;
+
+// How many updates a lagging spectator can fall behind before they start
+// missing deltas (`broadcast::Receiver::recv` then returns `Lagged`, at
+// which point `GameRelay::subscribe` would need to reseed them with a fresh
+// `Replay` — not yet wired up, since no spectator has hit this in practice).
+const CHANNEL_CAPACITY: usize = 64;
+
+// Fans a single authoritative game out to every spectator watching it: the
+// host publishes each `ServerToClientMessage` once and every subscriber
+// gets a clone of it off the same broadcast channel (the relay does not
+// serialize it; each spectator's `ClientSink` encodes its own copy), instead of the
+// baseline behavior of running an independent `GameAsPlayer` simulation per
+// spectator connection.
+pub struct GameRelay {
+  version: String,
+  tx: broadcast::Sender<ServerToClientMessage>,
+  // The most recent state, so a spectator who subscribes mid-game can be
+  // seeded with a `Replay` instead of waiting for the next delta (or
+  // missing everything that happened before they joined).
+  latest: Mutex<GameSnapshot>,
+}
+
+impl GameRelay {
+  pub fn new(version: String, initial_snapshot: GameSnapshot) -> Self {
+    let (tx, _rx) = broadcast::channel(CHANNEL_CAPACITY);
+    GameRelay {
+      version,
+      tx,
+      latest: Mutex::new(initial_snapshot),
+    }
+  }
+
+  // Sends `message` once on the broadcast channel, from which every current
+  // subscriber receives its own clone; nothing is serialized here.
+  // Subscribers that have disconnected are simply dropped by
+  // `broadcast::Sender`; there's no per-spectator work here. Holds `latest`
+  // for the update-then-send so a concurrent `subscribe` can't land between
+  // them and seed a spectator with a snapshot that's already stale on the
+  // channel they just joined.
+  pub fn publish(&self, message: ServerToClientMessage) {
+    let mut latest = self.latest.lock().unwrap();
+    if let ServerToClientMessage::StateDelta(snapshot) = &message {
+      *latest = snapshot.clone();
+    }
+    // No subscribers is not an error: a relay with nobody watching yet
+    // should keep running, not refuse to publish.
+    let _ = self.tx.send(message);
+  }
+
+  // Subscribes a new spectator, seeded with the relay's current state as a
+  // `Replay` so they don't have to wait for the next delta to see anything,
+  // before handing back the live stream everyone else is also reading from.
+  // Reads `latest` and registers on `tx` under the same lock `publish` holds,
+  // so a concurrent publish can't land in the gap between the two and leave
+  // the spectator's seed and live stream disagreeing about the current state.
+  pub fn subscribe(&self) -> (ServerToClientMessage, broadcast::Receiver<ServerToClientMessage>) {
+    let latest = self.latest.lock().unwrap();
+    let seed = ServerToClientMessage::Replay(RecordedGame::new(
+      self.version.clone(),
+      latest.clone(),
+    ));
+    (seed, self.tx.subscribe())
+  }
+}